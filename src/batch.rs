@@ -0,0 +1,54 @@
+//! Batched JSON-RPC requests
+//!
+//! This module contains [`Batch`], a builder that accumulates several Electrum calls to be sent
+//! to the server in a single round trip through
+//! [`Client::batch_call`](../client/struct.Client.html#method.batch_call).
+
+use bitcoin::{Script, Txid};
+
+use crate::types::{to_hex, Param, ToElectrumScriptHash};
+
+/// Accumulates method/params pairs to be issued as a single JSON-RPC batch request.
+#[derive(Debug, Default, Clone)]
+pub struct Batch {
+    calls: Vec<(&'static str, Vec<Param>)>,
+}
+
+impl Batch {
+    /// Creates a new, empty batch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a `blockchain.scripthash.listunspent` call.
+    pub fn script_list_unspent(&mut self, script: &Script) {
+        let params = vec![Param::String(to_hex(&script.to_electrum_scripthash()))];
+        self.calls.push(("blockchain.scripthash.listunspent", params));
+    }
+
+    /// Queues a `blockchain.scripthash.get_history` call.
+    pub fn script_get_history(&mut self, script: &Script) {
+        let params = vec![Param::String(to_hex(&script.to_electrum_scripthash()))];
+        self.calls.push(("blockchain.scripthash.get_history", params));
+    }
+
+    /// Queues a `blockchain.transaction.get` call.
+    pub fn transaction_get(&mut self, txid: &Txid) {
+        let params = vec![Param::String(txid.to_string())];
+        self.calls.push(("blockchain.transaction.get", params));
+    }
+
+    /// Number of calls currently queued in the batch.
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    /// Whether the batch has no calls queued.
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    pub(crate) fn into_requests(self) -> Vec<(&'static str, Vec<Param>)> {
+        self.calls
+    }
+}
@@ -0,0 +1,238 @@
+//! Electrum client
+//!
+//! This module contains the main [`Client`] used to send requests to an Electrum server and
+//! parse their responses.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+
+use bitcoin::hashes::hex::FromHex;
+use bitcoin::{Script, Txid};
+
+use crate::batch::Batch;
+use crate::types::{
+    to_hex, Error, FeeHistogram, FeeRate, GetBalanceRes, GetHeadersRes, GetHistoryRes,
+    GetMerkleRes, HeaderNotification, ListUnspentRes, Param, Request, ScriptHash, ScriptStatus,
+    ServerFeaturesRes, ToElectrumScriptHash,
+};
+
+/// Client to talk to an Electrum server over a plaintext TCP connection.
+pub struct Client {
+    stream: Mutex<BufReader<TcpStream>>,
+    next_id: AtomicUsize,
+}
+
+impl Client {
+    /// Opens a plaintext TCP connection to the Electrum server at `socket_addr`.
+    pub async fn new(socket_addr: &str) -> Result<Self, Error> {
+        let stream = TcpStream::connect(socket_addr).await?;
+
+        Ok(Self {
+            stream: Mutex::new(BufReader::new(stream)),
+            next_id: AtomicUsize::new(0),
+        })
+    }
+
+    /// Opens a TCP connection to the Electrum server at `socket_addr`, routed through the SOCKS5
+    /// proxy described by `proxy`. This is the standard way to reach `.onion` Electrum servers
+    /// over Tor and to avoid leaking the client's IP to the server operator.
+    #[cfg(feature = "proxy")]
+    pub async fn new_with_proxy(socket_addr: &str, proxy: &crate::socks::ProxyConfig) -> Result<Self, Error> {
+        let stream = crate::socks::connect(socket_addr, proxy).await?;
+
+        Ok(Self {
+            stream: Mutex::new(BufReader::new(stream)),
+            next_id: AtomicUsize::new(0),
+        })
+    }
+
+    fn next_id(&self) -> usize {
+        self.next_id.fetch_add(1, Ordering::SeqCst)
+    }
+
+    /// Sends a single JSON-RPC request to the server and parses the result into `T`.
+    pub async fn call<T>(&self, method: &str, params: Vec<Param>) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let req = Request::new_id(self.next_id(), method, params);
+
+        let mut stream = self.stream.lock().await;
+        let raw_req = serde_json::to_vec(&req)?;
+        stream.get_mut().write_all(&raw_req).await?;
+        stream.get_mut().write_all(b"\n").await?;
+
+        let mut line = String::new();
+        stream.read_line(&mut line).await?;
+        drop(stream);
+
+        let raw_resp: Value = serde_json::from_str(&line)?;
+        parse_result(raw_resp)
+    }
+
+    /// Sends a [`Batch`] of requests to the server in a single round trip.
+    ///
+    /// The responses are re-ordered to match the order in which the calls were added to the
+    /// batch, regardless of the order in which the server answered them. If the server returned
+    /// an error object for one of the calls, the corresponding slot in the result is `Err` while
+    /// the other, successful calls are still returned as `Ok`.
+    pub async fn batch_call(&self, batch: Batch) -> Result<Vec<Result<Value, Error>>, Error> {
+        let calls = batch.into_requests();
+        if calls.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut id_to_index = HashMap::with_capacity(calls.len());
+        let requests: Vec<Request> = calls
+            .into_iter()
+            .enumerate()
+            .map(|(index, (method, params))| {
+                let id = self.next_id();
+                id_to_index.insert(id, index);
+                Request::new_id(id, method, params)
+            })
+            .collect();
+
+        let mut stream = self.stream.lock().await;
+        let raw_req = serde_json::to_vec(&requests)?;
+        stream.get_mut().write_all(&raw_req).await?;
+        stream.get_mut().write_all(b"\n").await?;
+
+        let mut line = String::new();
+        stream.read_line(&mut line).await?;
+        drop(stream);
+
+        let raw_resps: Vec<Value> = serde_json::from_str(&line)?;
+        let mut results: Vec<Option<Result<Value, Error>>> =
+            (0..id_to_index.len()).map(|_| None).collect();
+
+        for raw_resp in raw_resps {
+            let id = raw_resp
+                .get("id")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| Error::InvalidResponse(raw_resp.clone()))? as usize;
+            let index = *id_to_index
+                .get(&id)
+                .ok_or_else(|| Error::InvalidResponse(raw_resp.clone()))?;
+
+            results[index] = Some(parse_result(raw_resp));
+        }
+
+        results
+            .into_iter()
+            .map(|slot| slot.unwrap_or_else(|| Err(Error::InvalidResponse(Value::Null))))
+            .collect()
+    }
+
+    /// Returns the features supported by the server.
+    pub async fn server_features(&self) -> Result<ServerFeaturesRes, Error> {
+        self.call("server.features", vec![]).await
+    }
+
+    /// Returns the confirmed and unconfirmed balance of a script.
+    pub async fn script_get_balance(&self, script: &Script) -> Result<GetBalanceRes, Error> {
+        let params = vec![Param::String(to_hex(&script.to_electrum_scripthash()))];
+        self.call("blockchain.scripthash.get_balance", params).await
+    }
+
+    /// Returns the history of transactions that involve a script.
+    pub async fn script_get_history(&self, script: &Script) -> Result<Vec<GetHistoryRes>, Error> {
+        let params = vec![Param::String(to_hex(&script.to_electrum_scripthash()))];
+        self.call("blockchain.scripthash.get_history", params).await
+    }
+
+    /// Returns the unspent outputs of a script.
+    pub async fn script_list_unspent(&self, script: &Script) -> Result<Vec<ListUnspentRes>, Error> {
+        let params = vec![Param::String(to_hex(&script.to_electrum_scripthash()))];
+        self.call("blockchain.scripthash.listunspent", params).await
+    }
+
+    /// Returns a raw transaction given its `txid`.
+    pub async fn transaction_get(&self, txid: &Txid) -> Result<bitcoin::Transaction, Error> {
+        let raw_tx: String = self
+            .call("blockchain.transaction.get", vec![Param::String(txid.to_string())])
+            .await?;
+
+        let bytes = Vec::<u8>::from_hex(&raw_tx)?;
+        Ok(bitcoin::consensus::deserialize(&bytes)?)
+    }
+
+    /// Returns the merkle inclusion proof for a confirmed transaction, given its `txid` and the
+    /// `height` of the block that confirmed it.
+    pub async fn transaction_get_merkle(
+        &self,
+        txid: &Txid,
+        height: usize,
+    ) -> Result<GetMerkleRes, Error> {
+        let params = vec![Param::String(txid.to_string()), Param::Usize(height)];
+        self.call("blockchain.transaction.get_merkle", params).await
+    }
+
+    /// Returns `count` block headers starting at `start_height`.
+    pub async fn block_headers(&self, start_height: usize, count: usize) -> Result<GetHeadersRes, Error> {
+        let params = vec![Param::Usize(start_height), Param::Usize(count)];
+        self.call("blockchain.block.headers", params).await
+    }
+
+    /// Estimates the fee rate, in BTC/kB, needed for a transaction to be confirmed within
+    /// `blocks` blocks.
+    pub async fn estimate_fee(&self, blocks: usize) -> Result<FeeRate, Error> {
+        self.call("blockchain.estimatefee", vec![Param::Usize(blocks)]).await
+    }
+
+    /// Returns the minimum fee rate, in BTC/kB, that the server's node will relay.
+    pub async fn relay_fee(&self) -> Result<FeeRate, Error> {
+        self.call("blockchain.relayfee", vec![]).await
+    }
+
+    /// Returns a histogram of the fee rates paid by the transactions currently in the server's
+    /// mempool, usable to decide on a fee rate for a replacement or a child-pays-for-parent
+    /// transaction.
+    pub async fn mempool_get_fee_histogram(&self) -> Result<FeeHistogram, Error> {
+        self.call("mempool.get_fee_histogram", vec![]).await
+    }
+
+    /// Subscribes to status change notifications for `scripthash`, returning its current status
+    /// if the server already knows of one.
+    pub async fn script_subscribe(
+        &self,
+        scripthash: &ScriptHash,
+    ) -> Result<Option<ScriptStatus>, Error> {
+        let params = vec![Param::String(to_hex(scripthash))];
+        self.call("blockchain.scripthash.subscribe", params).await
+    }
+
+    /// Unsubscribes from status change notifications for `scripthash`.
+    pub async fn script_unsubscribe(&self, scripthash: &ScriptHash) -> Result<bool, Error> {
+        let params = vec![Param::String(to_hex(scripthash))];
+        self.call("blockchain.scripthash.unsubscribe", params).await
+    }
+
+    /// Subscribes to new block header notifications, returning the current tip.
+    pub async fn block_headers_subscribe(&self) -> Result<HeaderNotification, Error> {
+        self.call("blockchain.headers.subscribe", vec![]).await
+    }
+}
+
+fn parse_result<T: DeserializeOwned>(raw_resp: Value) -> Result<T, Error> {
+    if let Some(err) = raw_resp.get("error").filter(|e| !e.is_null()) {
+        return Err(match serde_json::from_value(err.clone()) {
+            Ok(rpc_err) => Error::Protocol(rpc_err),
+            Err(_) => Error::ProtocolInvalid(err.clone()),
+        });
+    }
+
+    let result = raw_resp
+        .get("result")
+        .cloned()
+        .ok_or_else(|| Error::InvalidResponse(raw_resp.clone()))?;
+
+    Ok(serde_json::from_value(result)?)
+}
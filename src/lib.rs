@@ -0,0 +1,19 @@
+//! Electrum Client
+//!
+//! This library provides an async client to issue requests to and parse the responses from
+//! Electrum servers. It also provides facilities to handle notifications sent by the server
+//! about new blocks or subscribed scripts.
+
+pub mod batch;
+pub mod client;
+pub mod reconnect;
+#[cfg(feature = "proxy")]
+pub mod socks;
+pub mod types;
+
+pub use batch::Batch;
+pub use client::Client;
+pub use reconnect::{ReconnectConfig, ReconnectingClient};
+#[cfg(feature = "proxy")]
+pub use socks::ProxyConfig;
+pub use types::*;
@@ -0,0 +1,232 @@
+//! Auto-reconnecting resilient client
+//!
+//! Electrum servers frequently drop idle connections. [`ReconnectingClient`] wraps a [`Client`],
+//! transparently re-dialing and replaying the caller's subscriptions after a disconnect, so a
+//! transient I/O error doesn't force the caller to rebuild the client and re-issue every
+//! `blockchain.scripthash.subscribe` by hand.
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use tokio::sync::RwLock;
+use tokio::time::sleep;
+
+use bitcoin::{Script, Txid};
+
+use crate::batch::Batch;
+use crate::client::Client;
+use crate::types::{
+    Error, FeeHistogram, FeeRate, GetBalanceRes, GetHeadersRes, GetHistoryRes, GetMerkleRes,
+    HeaderNotification, ListUnspentRes, Param, ScriptHash, ScriptStatus, ServerFeaturesRes,
+};
+
+/// Controls how a [`ReconnectingClient`] retries a dropped connection.
+#[derive(Debug, Clone)]
+pub struct ReconnectConfig {
+    /// Maximum number of dial attempts before giving up and returning the last error.
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled after each subsequent failed attempt.
+    pub initial_backoff: Duration,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Runs `$self.$method($($arg),*)` against the current inner [`Client`], reconnecting and
+/// retrying once if it fails with a transient [`Error::IOError`].
+///
+/// The read guard on the inner client is dropped before a reconnect is attempted, since
+/// `reconnect` needs to acquire the write lock to install the freshly re-dialed client. Arguments
+/// are written out twice (once per attempt), so pass `.clone()`-ed expressions for anything that
+/// isn't `Copy`.
+macro_rules! retry_once {
+    ($self:expr, $method:ident ( $($arg:expr),* $(,)? )) => {{
+        let result = { $self.client.read().await.$method($($arg),*).await };
+
+        match result {
+            Err(Error::IOError(_)) => {
+                $self.reconnect().await?;
+                $self.client.read().await.$method($($arg),*).await
+            }
+            other => other,
+        }
+    }};
+}
+
+/// A [`Client`] wrapper that transparently reconnects and replays subscriptions after the
+/// underlying connection is dropped.
+///
+/// Any request that fails with [`Error::IOError`] is retried once, against a freshly re-dialed
+/// connection, before the error is propagated to the caller.
+pub struct ReconnectingClient {
+    socket_addr: String,
+    config: ReconnectConfig,
+    client: RwLock<Client>,
+    subscribed_scripts: RwLock<HashSet<ScriptHash>>,
+    headers_subscribed: RwLock<bool>,
+}
+
+impl ReconnectingClient {
+    /// Connects to `socket_addr`, using `config` to control future reconnection attempts.
+    pub async fn new(socket_addr: &str, config: ReconnectConfig) -> Result<Self, Error> {
+        let client = Client::new(socket_addr).await?;
+
+        Ok(Self {
+            socket_addr: socket_addr.to_string(),
+            config,
+            client: RwLock::new(client),
+            subscribed_scripts: RwLock::new(HashSet::new()),
+            headers_subscribed: RwLock::new(false),
+        })
+    }
+
+    /// Sends a single JSON-RPC request, retrying once against a freshly re-dialed connection if
+    /// it fails with a transient I/O error.
+    pub async fn call<T>(&self, method: &str, params: Vec<Param>) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        retry_once!(self, call(method, params.clone()))
+    }
+
+    /// Sends a [`Batch`] of requests, retrying once against a freshly re-dialed connection if the
+    /// whole batch fails with a transient I/O error.
+    pub async fn batch_call(&self, batch: Batch) -> Result<Vec<Result<Value, Error>>, Error> {
+        retry_once!(self, batch_call(batch.clone()))
+    }
+
+    /// Returns the features supported by the server, retrying once on a transient I/O error.
+    pub async fn server_features(&self) -> Result<ServerFeaturesRes, Error> {
+        retry_once!(self, server_features())
+    }
+
+    /// Returns the confirmed and unconfirmed balance of a script, retrying once on a transient
+    /// I/O error.
+    pub async fn script_get_balance(&self, script: &Script) -> Result<GetBalanceRes, Error> {
+        retry_once!(self, script_get_balance(script))
+    }
+
+    /// Returns the history of transactions that involve a script, retrying once on a transient
+    /// I/O error.
+    pub async fn script_get_history(&self, script: &Script) -> Result<Vec<GetHistoryRes>, Error> {
+        retry_once!(self, script_get_history(script))
+    }
+
+    /// Returns the unspent outputs of a script, retrying once on a transient I/O error.
+    pub async fn script_list_unspent(&self, script: &Script) -> Result<Vec<ListUnspentRes>, Error> {
+        retry_once!(self, script_list_unspent(script))
+    }
+
+    /// Returns a raw transaction given its `txid`, retrying once on a transient I/O error.
+    pub async fn transaction_get(&self, txid: &Txid) -> Result<bitcoin::Transaction, Error> {
+        retry_once!(self, transaction_get(txid))
+    }
+
+    /// Returns the merkle inclusion proof for a confirmed transaction, retrying once on a
+    /// transient I/O error.
+    pub async fn transaction_get_merkle(
+        &self,
+        txid: &Txid,
+        height: usize,
+    ) -> Result<GetMerkleRes, Error> {
+        retry_once!(self, transaction_get_merkle(txid, height))
+    }
+
+    /// Returns `count` block headers starting at `start_height`, retrying once on a transient I/O
+    /// error.
+    pub async fn block_headers(&self, start_height: usize, count: usize) -> Result<GetHeadersRes, Error> {
+        retry_once!(self, block_headers(start_height, count))
+    }
+
+    /// Estimates the fee rate, in BTC/kB, needed for a transaction to be confirmed within
+    /// `blocks` blocks, retrying once on a transient I/O error.
+    pub async fn estimate_fee(&self, blocks: usize) -> Result<FeeRate, Error> {
+        retry_once!(self, estimate_fee(blocks))
+    }
+
+    /// Returns the minimum fee rate, in BTC/kB, that the server's node will relay, retrying once
+    /// on a transient I/O error.
+    pub async fn relay_fee(&self) -> Result<FeeRate, Error> {
+        retry_once!(self, relay_fee())
+    }
+
+    /// Returns a histogram of the fee rates paid by transactions in the server's mempool,
+    /// retrying once on a transient I/O error.
+    pub async fn mempool_get_fee_histogram(&self) -> Result<FeeHistogram, Error> {
+        retry_once!(self, mempool_get_fee_histogram())
+    }
+
+    /// Subscribes to status notifications for `scripthash`. The subscription is recorded so it
+    /// is automatically replayed if the connection is later dropped and re-established.
+    pub async fn script_subscribe(
+        &self,
+        scripthash: ScriptHash,
+    ) -> Result<Option<ScriptStatus>, Error> {
+        let result = retry_once!(self, script_subscribe(&scripthash))?;
+
+        self.subscribed_scripts.write().await.insert(scripthash);
+        Ok(result)
+    }
+
+    /// Unsubscribes from status notifications for `scripthash`.
+    pub async fn script_unsubscribe(&self, scripthash: &ScriptHash) -> Result<bool, Error> {
+        let result = retry_once!(self, script_unsubscribe(scripthash))?;
+
+        self.subscribed_scripts.write().await.remove(scripthash);
+        Ok(result)
+    }
+
+    /// Subscribes to new block header notifications. The subscription is recorded so it is
+    /// automatically replayed if the connection is later dropped and re-established.
+    pub async fn block_headers_subscribe(&self) -> Result<HeaderNotification, Error> {
+        let result = retry_once!(self, block_headers_subscribe())?;
+
+        *self.headers_subscribed.write().await = true;
+        Ok(result)
+    }
+
+    /// Re-dials the server, retrying with an exponential backoff up to `config.max_retries`
+    /// times, then replays every script and header subscription recorded so far.
+    async fn reconnect(&self) -> Result<(), Error> {
+        let mut backoff = self.config.initial_backoff;
+        let mut last_err = None;
+
+        for _ in 0..self.config.max_retries {
+            match Client::new(&self.socket_addr).await {
+                Ok(fresh) => {
+                    *self.client.write().await = fresh;
+                    last_err = None;
+                    break;
+                }
+                Err(err) => {
+                    last_err = Some(err);
+                    sleep(backoff).await;
+                    backoff *= 2;
+                }
+            }
+        }
+
+        if let Some(err) = last_err {
+            return Err(err);
+        }
+
+        let client = self.client.read().await;
+        for scripthash in self.subscribed_scripts.read().await.iter() {
+            client.script_subscribe(scripthash).await?;
+        }
+        if *self.headers_subscribed.read().await {
+            client.block_headers_subscribe().await?;
+        }
+
+        Ok(())
+    }
+}
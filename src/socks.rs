@@ -0,0 +1,54 @@
+//! SOCKS5 proxy support
+//!
+//! Enabled by the `proxy` feature. Lets [`Client::new_with_proxy`](../client/struct.Client.html#method.new_with_proxy)
+//! route the connection to the Electrum server through a SOCKS5 proxy, which is the standard way
+//! to reach `.onion` Electrum servers over Tor and to avoid leaking the client's IP to the server
+//! operator.
+
+use tokio::net::TcpStream;
+use tokio_socks::tcp::Socks5Stream;
+
+use crate::types::Error;
+
+/// Address and optional credentials of a SOCKS5 proxy to dial the Electrum server through.
+#[derive(Debug, Clone)]
+pub struct ProxyConfig {
+    /// Address of the SOCKS5 proxy, e.g. `127.0.0.1:9050` for a local Tor daemon.
+    pub proxy_addr: String,
+    /// Optional username/password used to authenticate with the proxy.
+    pub credentials: Option<(String, String)>,
+}
+
+impl ProxyConfig {
+    /// Creates a proxy configuration without authentication.
+    pub fn new(proxy_addr: impl Into<String>) -> Self {
+        Self {
+            proxy_addr: proxy_addr.into(),
+            credentials: None,
+        }
+    }
+
+    /// Sets the username/password used to authenticate with the proxy.
+    pub fn with_credentials(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.credentials = Some((username.into(), password.into()));
+        self
+    }
+}
+
+pub(crate) async fn connect(socket_addr: &str, proxy: &ProxyConfig) -> Result<TcpStream, Error> {
+    let stream = match &proxy.credentials {
+        Some((username, password)) => {
+            Socks5Stream::connect_with_password(
+                proxy.proxy_addr.as_str(),
+                socket_addr,
+                username.as_str(),
+                password.as_str(),
+            )
+            .await
+        }
+        None => Socks5Stream::connect(proxy.proxy_addr.as_str(), socket_addr).await,
+    }
+    .map_err(Error::ProxyError)?;
+
+    Ok(stream.into_inner())
+}
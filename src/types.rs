@@ -4,8 +4,8 @@
 
 use bitcoin::blockdata::block;
 use bitcoin::hashes::hex::FromHex;
-use bitcoin::hashes::{sha256, Hash};
-use bitcoin::{Script, Txid};
+use bitcoin::hashes::{sha256, sha256d, Hash};
+use bitcoin::{Script, Txid, TxMerkleNode};
 
 use serde::{de, Deserialize, Serialize};
 
@@ -121,6 +121,11 @@ where
     deserialize(&vec).map_err(de::Error::custom)
 }
 
+/// Encodes a byte slice as a lowercase hex string, for use as a JSON-RPC string parameter.
+pub(crate) fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
 /// Response to a [`script_get_history`](../client/struct.Client.html#method.script_get_history) request.
 #[derive(Debug, Deserialize)]
 pub struct GetHistoryRes {
@@ -164,21 +169,81 @@ pub struct ServerFeaturesRes {
     pub pruning: Option<i64>,
 }
 
-/// Response to a [`server_features`](../client/struct.Client.html#method.server_features) request.
-#[derive(Debug, Deserialize)]
+/// Response to a [`block_headers`](../client/struct.Client.html#method.block_headers) request.
+#[derive(Debug)]
 pub struct GetHeadersRes {
     /// Maximum number of headers returned in a single response.
     pub max: usize,
     /// Number of headers in this response.
     pub count: usize,
     /// Raw headers concatenated. Normally cleared before returning.
-    #[serde(rename(deserialize = "hex"), deserialize_with = "from_hex")]
     pub raw_headers: Vec<u8>,
-    /// Array of block headers.
-    #[serde(skip)]
+    /// Array of block headers, parsed out of `raw_headers` in 80-byte chunks.
     pub headers: Vec<block::BlockHeader>,
 }
 
+impl<'de> de::Deserialize<'de> for GetHeadersRes {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Raw {
+            max: usize,
+            count: usize,
+            #[serde(rename = "hex", deserialize_with = "from_hex")]
+            raw_headers: Vec<u8>,
+        }
+
+        let raw = Raw::deserialize(deserializer)?;
+        let headers = raw
+            .raw_headers
+            .chunks(80)
+            .map(|chunk| bitcoin::consensus::deserialize(chunk).map_err(de::Error::custom))
+            .collect::<Result<Vec<block::BlockHeader>, D::Error>>()?;
+
+        Ok(GetHeadersRes {
+            max: raw.max,
+            count: raw.count,
+            raw_headers: raw.raw_headers,
+            headers,
+        })
+    }
+}
+
+impl GetHeadersRes {
+    /// Verifies that every header in [`Self::headers`] links to the previous one through
+    /// `prev_blockhash` and satisfies its own proof-of-work target, treating the first header as
+    /// being at `start_height`.
+    ///
+    /// Returns a descriptive [`Error`] identifying the first broken link, if any.
+    pub fn verify_chain(&self, start_height: usize) -> Result<(), Error> {
+        for (i, header) in self.headers.iter().enumerate() {
+            let height = start_height + i;
+
+            if header.validate_pow(&header.target()).is_err() {
+                return Err(Error::Message(format!(
+                    "header at height {} does not satisfy its proof-of-work target",
+                    height
+                )));
+            }
+
+            if i > 0 {
+                let prev = &self.headers[i - 1];
+                if header.prev_blockhash != prev.block_hash() {
+                    return Err(Error::Message(format!(
+                        "header at height {} does not chain to the header at height {}",
+                        height,
+                        height - 1
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// Response to a [`script_get_balance`](../client/struct.Client.html#method.script_get_balance) request.
 #[derive(Debug, Deserialize)]
 pub struct GetBalanceRes {
@@ -188,6 +253,43 @@ pub struct GetBalanceRes {
     pub unconfirmed: u64,
 }
 
+/// Response to an [`estimate_fee`](../client/struct.Client.html#method.estimate_fee) or
+/// [`relay_fee`](../client/struct.Client.html#method.relay_fee) request: a fee rate expressed in
+/// BTC/kB.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct FeeRate(pub f64);
+
+/// Response to a [`mempool_get_fee_histogram`](../client/struct.Client.html#method.mempool_get_fee_histogram) request.
+///
+/// Each entry is a `(fee_rate, vsize)` pair, where `fee_rate` is a fee rate in sat/vB and `vsize`
+/// is the virtual size, in bytes, of the transactions in this fee bucket (i.e. paying a fee rate
+/// between this entry's and the previous one's). The array is ordered from the highest fee rate
+/// down to the lowest, so [`fee_rate_for_target`](Self::fee_rate_for_target) sums `vsize` across
+/// entries to find the cumulative depth.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct FeeHistogram(pub Vec<(f64, u32)>);
+
+impl FeeHistogram {
+    /// Returns the fee rate, in sat/vB, needed for a transaction to be included within the first
+    /// `target_vsize` bytes worth of the mempool, according to this histogram.
+    ///
+    /// Returns `None` if `target_vsize` falls beyond the portion of the mempool covered by the
+    /// histogram.
+    pub fn fee_rate_for_target(&self, target_vsize: u32) -> Option<f64> {
+        let mut cumulative = 0u32;
+        for (fee_rate, vsize) in &self.0 {
+            cumulative = cumulative.saturating_add(*vsize);
+            if cumulative >= target_vsize {
+                return Some(*fee_rate);
+            }
+        }
+
+        None
+    }
+}
+
 /// Response to a [`transaction_get_merkle`](../client/struct.Client.html#method.transaction_get_merkle) request.
 #[derive(Debug, Deserialize)]
 pub struct GetMerkleRes {
@@ -200,6 +302,47 @@ pub struct GetMerkleRes {
     pub merkle: Vec<[u8; 32]>,
 }
 
+/// Verifies the merkle inclusion proof returned by a
+/// [`transaction_get_merkle`](../client/struct.Client.html#method.transaction_get_merkle) request
+/// against the merkle root of the block that is claimed to have confirmed `txid`.
+///
+/// `merkle_root` is expected in the crate's internal byte order, i.e. `block::BlockHeader`'s own
+/// `merkle_root` field — including the headers `GetHeadersRes` parses out of
+/// `blockchain.block.headers` — and the same convention `GetHeadersRes::verify_chain` uses when
+/// comparing `prev_blockhash`/`block_hash()`. This is *not* the display/hex order used elsewhere
+/// on the wire. `res.merkle` still holds the sibling hashes exactly as sent by Electrum, in
+/// display order, and is flipped to internal order before hashing. Returns `Ok(true)` if the
+/// computed root matches `merkle_root`.
+pub fn verify_merkle_proof(
+    txid: &Txid,
+    merkle_root: &TxMerkleNode,
+    res: &GetMerkleRes,
+) -> Result<bool, Error> {
+    let mut current = (*txid).into_inner();
+    let mut pos = res.pos;
+
+    for sibling in &res.merkle {
+        // Flip the sibling from Electrum's display order back to the internal order used while
+        // hashing.
+        let mut sibling = *sibling;
+        sibling.reverse();
+
+        let mut engine = sha256d::Hash::engine();
+        if pos & 1 == 0 {
+            engine.input(&current);
+            engine.input(&sibling);
+        } else {
+            engine.input(&sibling);
+            engine.input(&current);
+        }
+        current = sha256d::Hash::from_engine(engine).into_inner();
+
+        pos >>= 1;
+    }
+
+    Ok(current == merkle_root.into_inner())
+}
+
 /// Notification of a new block header
 #[derive(Debug, Deserialize)]
 pub struct HeaderNotification {
@@ -219,6 +362,18 @@ pub struct ScriptNotification {
     pub status: ScriptStatus,
 }
 
+/// A JSON-RPC 2.0 error object, as returned by the `error` field of a response.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RpcError {
+    /// Numeric error code. Electrum servers commonly reuse the daemon's own error codes (e.g.
+    /// `-26` for a transaction rejected by policy) alongside their own.
+    pub code: i64,
+    /// Human-readable description of the error.
+    pub message: String,
+    /// Optional extra data attached to the error, if any.
+    pub data: Option<serde_json::Value>,
+}
+
 /// Errors
 #[derive(Debug)]
 pub enum Error {
@@ -228,8 +383,11 @@ pub enum Error {
     JSON(serde_json::error::Error),
     /// Wraps `bitcoin::hashes::hex::Error`
     Hex(bitcoin::hashes::hex::Error),
-    /// Error returned by the Electrum server
-    Protocol(serde_json::Value),
+    /// Error returned by the Electrum server, parsed into a structured JSON-RPC 2.0 error object
+    Protocol(RpcError),
+    /// Error returned by the server in a shape that doesn't conform to the JSON-RPC 2.0 error
+    /// object (missing `code` or `message`). Kept as a fallback for non-conforming servers.
+    ProtocolInvalid(serde_json::Value),
     /// Error during the deserialization of a Bitcoin data structure
     Bitcoin(bitcoin::consensus::encode::Error),
     /// Already subscribed to the notifications of an address
@@ -251,6 +409,10 @@ pub enum Error {
     #[cfg(feature = "use-openssl")]
     /// SSL Handshake failed with the server
     SslHandshakeError(openssl::ssl::HandshakeError<std::net::TcpStream>),
+
+    #[cfg(feature = "proxy")]
+    /// Failed to establish a connection to the server through the configured SOCKS5 proxy
+    ProxyError(tokio_socks::Error),
 }
 
 macro_rules! impl_error {
@@ -267,3 +429,122 @@ impl_error!(std::io::Error, IOError);
 impl_error!(serde_json::Error, JSON);
 impl_error!(bitcoin::hashes::hex::Error, Hex);
 impl_error!(bitcoin::consensus::encode::Error, Bitcoin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Synthetic but internally-consistent 2-level proof: `txid` and both siblings are
+    // arbitrary fixed byte patterns (no real chain data), combined by hand with sha256d to
+    // derive `root`, so the vector exercises both branches of the `pos & 1` ordering and a
+    // genuine (non-palindromic) byte reversal on each sibling.
+    fn merkle_fixture() -> (Txid, TxMerkleNode, GetMerkleRes) {
+        let txid_internal: [u8; 32] = {
+            let mut b = [0u8; 32];
+            for (i, byte) in b.iter_mut().enumerate() {
+                *byte = (i + 1) as u8;
+            }
+            b
+        };
+        let mut sib1_internal = [0u8; 32];
+        let mut sib2_internal = [0u8; 32];
+        for (i, byte) in sib1_internal.iter_mut().enumerate() {
+            *byte = (0x40 + i) as u8;
+        }
+        for (i, byte) in sib2_internal.iter_mut().enumerate() {
+            *byte = (0x60 + i) as u8;
+        }
+
+        let mut sib1_wire = sib1_internal;
+        sib1_wire.reverse();
+        let mut sib2_wire = sib2_internal;
+        sib2_wire.reverse();
+
+        let root_internal: [u8; 32] = [
+            0x16, 0x85, 0x4c, 0xe3, 0x25, 0x88, 0xc6, 0x6e, 0xe2, 0x48, 0xa9, 0x0f, 0xd7, 0xa9,
+            0xd4, 0x9c, 0x05, 0xe2, 0x49, 0x4f, 0xc0, 0x0f, 0x94, 0x16, 0x38, 0x25, 0xb2, 0x39,
+            0x64, 0xae, 0x0e, 0x16,
+        ];
+
+        let txid = Txid::from_slice(&txid_internal).unwrap();
+        let merkle_root = TxMerkleNode::from_slice(&root_internal).unwrap();
+        let res = GetMerkleRes {
+            block_height: 1,
+            pos: 0b10,
+            merkle: vec![sib1_wire, sib2_wire],
+        };
+
+        (txid, merkle_root, res)
+    }
+
+    #[test]
+    fn verify_merkle_proof_accepts_a_valid_proof() {
+        let (txid, merkle_root, res) = merkle_fixture();
+        assert_eq!(verify_merkle_proof(&txid, &merkle_root, &res).unwrap(), true);
+    }
+
+    #[test]
+    fn verify_merkle_proof_rejects_a_mismatched_root() {
+        let (txid, merkle_root, res) = merkle_fixture();
+
+        let mut bad_root_internal = merkle_root.into_inner();
+        bad_root_internal[0] ^= 0xff;
+        let bad_root = TxMerkleNode::from_slice(&bad_root_internal).unwrap();
+
+        assert_eq!(verify_merkle_proof(&txid, &bad_root, &res).unwrap(), false);
+    }
+
+    // Two headers mined against an easy regtest-style target (bits `0x207fffff`), the second
+    // chaining onto the first's hash, so `verify_chain` can be exercised without a live server.
+    const RAW_HEADERS_HEX: &str = "0100000000000000000000000000000000000000000000000000000000000000000000000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f2000f15365ffff7f2002000000010000006401bee3d0a7a6d394d9be9e8c66d7ef3631062eced2038a3ec61e6633d41c252122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f4058f35365ffff7f2000000000";
+
+    fn parse_headers(raw_headers: Vec<u8>) -> GetHeadersRes {
+        let headers = raw_headers
+            .chunks(80)
+            .map(|chunk| bitcoin::consensus::deserialize(chunk).unwrap())
+            .collect();
+
+        GetHeadersRes {
+            max: 2,
+            count: 2,
+            raw_headers,
+            headers,
+        }
+    }
+
+    #[test]
+    fn verify_chain_accepts_a_linked_and_valid_segment() {
+        let raw_headers = Vec::<u8>::from_hex(RAW_HEADERS_HEX).unwrap();
+        let res = parse_headers(raw_headers);
+
+        assert!(res.verify_chain(100).is_ok());
+    }
+
+    #[test]
+    fn verify_chain_rejects_a_broken_link() {
+        let mut raw_headers = Vec::<u8>::from_hex(RAW_HEADERS_HEX).unwrap();
+        // Flip a byte inside the second header's `prev_blockhash` field (offset 4..36 within
+        // the header, i.e. right after the 4-byte version) so it no longer matches the first
+        // header's hash. This particular flip still leaves the second header under its
+        // proof-of-work target, so the error comes from the link check.
+        raw_headers[80 + 5] ^= 0xff;
+        let res = parse_headers(raw_headers);
+
+        assert!(res.verify_chain(100).is_err());
+    }
+
+    #[test]
+    fn fee_rate_for_target_sums_vsize_until_the_target_depth_is_reached() {
+        let histogram = FeeHistogram(vec![(25.0, 10_000), (10.0, 20_000), (2.0, 30_000)]);
+
+        // Falls within the first bucket.
+        assert_eq!(histogram.fee_rate_for_target(5_000), Some(25.0));
+        // Exactly at a bucket boundary.
+        assert_eq!(histogram.fee_rate_for_target(10_000), Some(25.0));
+        // Requires summing across buckets.
+        assert_eq!(histogram.fee_rate_for_target(25_000), Some(10.0));
+        assert_eq!(histogram.fee_rate_for_target(60_000), Some(2.0));
+        // Deeper than the histogram covers.
+        assert_eq!(histogram.fee_rate_for_target(60_001), None);
+    }
+}